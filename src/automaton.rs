@@ -0,0 +1,240 @@
+use rand::seq::SliceRandom;
+
+pub type State = u8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RuleCellFrom {
+    Any,
+    One(State),
+    Group(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RuleCellTo {
+    None,
+    One(State),
+    GroupRandom(usize),
+    // Offset (dx, dy) from the rule's center, in pattern space: copy whatever
+    // state was matched at that other pattern position.
+    Copy(i32, i32),
+}
+
+// A width x height neighborhood pattern, row-major, matched centered on each
+// grid cell against the previous tick's buffer; the pattern cell at the
+// center gives the new state for that grid cell.
+#[derive(Clone)]
+pub struct Rule {
+    pub width: usize,
+    pub height: usize,
+    pub pattern: Vec<(RuleCellFrom, RuleCellTo)>,
+}
+
+impl Rule {
+    fn center_offset(&self) -> (i32, i32) {
+        ((self.width / 2) as i32, (self.height / 2) as i32)
+    }
+}
+
+#[derive(Default)]
+pub struct Automaton {
+    pub rules: Vec<Rule>,
+    pub cell_groups: Vec<Vec<State>>,
+}
+
+pub fn quantize(density: f32, num_states: u32) -> State {
+    let levels = num_states.max(1) - 1;
+    (density.clamp(0.0, 1.0) * levels as f32).round() as State
+}
+
+pub fn dequantize(state: State, num_states: u32) -> f32 {
+    let levels = num_states.max(1) - 1;
+    if levels == 0 {
+        0.0
+    } else {
+        state as f32 / levels as f32
+    }
+}
+
+impl Automaton {
+    fn group_contains(&self, idx: usize, state: State) -> bool {
+        self.cell_groups
+            .get(idx)
+            .map_or(false, |group| group.contains(&state))
+    }
+
+    // Does `rule`'s pattern, centered at (cx, cy), match the previous buffer?
+    fn matches(
+        &self,
+        rule: &Rule,
+        get: impl Fn(i32, i32) -> Option<State>,
+        cx: i32,
+        cy: i32,
+    ) -> bool {
+        let (half_w, half_h) = rule.center_offset();
+        for py in 0..rule.height {
+            for px in 0..rule.width {
+                let (from, _) = rule.pattern[py * rule.width + px];
+                if from == RuleCellFrom::Any {
+                    continue;
+                }
+                let gx = cx + px as i32 - half_w;
+                let gy = cy + py as i32 - half_h;
+                let state = get(gx, gy);
+                let ok = match from {
+                    RuleCellFrom::Any => true,
+                    RuleCellFrom::One(s) => state == Some(s),
+                    RuleCellFrom::Group(idx) => {
+                        state.map_or(false, |s| self.group_contains(idx, s))
+                    }
+                };
+                if !ok {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn resolve(
+        &self,
+        rule: &Rule,
+        get: impl Fn(i32, i32) -> Option<State>,
+        cx: i32,
+        cy: i32,
+        rng: &mut impl rand::Rng,
+    ) -> Option<State> {
+        let (half_w, half_h) = rule.center_offset();
+        let (_, to) = rule.pattern[half_h as usize * rule.width + half_w as usize];
+        match to {
+            RuleCellTo::None => None,
+            RuleCellTo::One(s) => Some(s),
+            RuleCellTo::GroupRandom(idx) => {
+                self.cell_groups.get(idx).and_then(|g| g.choose(rng).copied())
+            }
+            RuleCellTo::Copy(dx, dy) => get(cx + dx, cy + dy),
+        }
+    }
+
+    // Evaluate every rule (first match wins) over the Moore neighborhood of
+    // each cell in `grid`, writing the result into `out`. `grid` and `out`
+    // must both be `width * height` long; matching always reads the
+    // untouched previous buffer, mirroring how `World::diffuse_pass` reads
+    // `cells` while writing into `cells_tmp` before swapping.
+    pub fn step(&self, grid: &[State], width: usize, height: usize, out: &mut [State]) {
+        let get = |gx: i32, gy: i32| -> Option<State> {
+            if gx < 0 || gy < 0 || gx >= width as i32 || gy >= height as i32 {
+                None
+            } else {
+                Some(grid[gy as usize * width + gx as usize])
+            }
+        };
+
+        let mut rng = rand::thread_rng();
+        for cy in 0..height as i32 {
+            for cx in 0..width as i32 {
+                let mut new_state = grid[cy as usize * width + cx as usize];
+                for rule in &self.rules {
+                    if self.matches(rule, get, cx, cy) {
+                        if let Some(s) = self.resolve(rule, get, cx, cy, &mut rng) {
+                            new_state = s;
+                        }
+                        break;
+                    }
+                }
+                out[cy as usize * width + cx as usize] = new_state;
+            }
+        }
+    }
+}
+
+// A small textual DSL for editing rules/groups in the GUI without needing a
+// bespoke widget per pattern cell:
+//
+//   GROUP <idx> = <state>,<state>,...
+//   RULE <w>x<h> | <from>:<to> <from>:<to> ...
+//
+// from: `*` (Any), an integer (One), or `g<idx>` (Group)
+// to:   `-` (None), an integer (One), `g<idx>` (GroupRandom), or `c<dx>,<dy>` (Copy)
+//
+// Malformed lines are skipped rather than aborting the whole parse, so a typo
+// on one rule doesn't throw away every other rule being edited.
+pub fn parse_rules_text(text: &str) -> (Vec<Rule>, Vec<Vec<State>>) {
+    let mut rules = Vec::new();
+    let mut groups = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("GROUP") {
+            if let Some((idx_str, states_str)) = rest.split_once('=') {
+                if let Ok(idx) = idx_str.trim().parse::<usize>() {
+                    let states: Vec<State> = states_str
+                        .split(',')
+                        .filter_map(|s| s.trim().parse().ok())
+                        .collect();
+                    if groups.len() <= idx {
+                        groups.resize(idx + 1, Vec::new());
+                    }
+                    groups[idx] = states;
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("RULE") {
+            if let Some(rule) = parse_rule_line(rest) {
+                rules.push(rule);
+            }
+        }
+    }
+
+    (rules, groups)
+}
+
+fn parse_rule_line(rest: &str) -> Option<Rule> {
+    let (dims, cells) = rest.split_once('|')?;
+    let (w_str, h_str) = dims.trim().split_once('x')?;
+    let width: usize = w_str.trim().parse().ok()?;
+    let height: usize = h_str.trim().parse().ok()?;
+
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let pattern: Vec<(RuleCellFrom, RuleCellTo)> = cells
+        .split_whitespace()
+        .filter_map(|token| {
+            let (from_str, to_str) = token.split_once(':')?;
+            Some((parse_from(from_str)?, parse_to(to_str)?))
+        })
+        .collect();
+
+    if pattern.len() != width * height {
+        return None;
+    }
+
+    Some(Rule {
+        width,
+        height,
+        pattern,
+    })
+}
+
+fn parse_from(s: &str) -> Option<RuleCellFrom> {
+    if s == "*" {
+        Some(RuleCellFrom::Any)
+    } else if let Some(idx) = s.strip_prefix('g') {
+        idx.parse().ok().map(RuleCellFrom::Group)
+    } else {
+        s.parse().ok().map(RuleCellFrom::One)
+    }
+}
+
+fn parse_to(s: &str) -> Option<RuleCellTo> {
+    if s == "-" {
+        Some(RuleCellTo::None)
+    } else if let Some(idx) = s.strip_prefix('g') {
+        idx.parse().ok().map(RuleCellTo::GroupRandom)
+    } else if let Some(rest) = s.strip_prefix('c') {
+        let (dx_str, dy_str) = rest.split_once(',')?;
+        Some(RuleCellTo::Copy(dx_str.parse().ok()?, dy_str.parse().ok()?))
+    } else {
+        s.parse().ok().map(RuleCellTo::One)
+    }
+}
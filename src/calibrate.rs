@@ -0,0 +1,266 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use rayon::prelude::*;
+
+use crate::fsm::PlantStateMachine;
+use crate::param::{ParameterMatrix, ResourceVector};
+use crate::state_pipe::GroundTruthStatePipe;
+
+// One point of a target population trajectory a calibration run is fit
+// against, e.g. sampled from field data or a prior deterministic run.
+#[derive(Clone, Copy)]
+pub struct PopObservation {
+    pub total_pop: f32,
+    pub mature_pop: f32,
+}
+
+pub enum StopCriterion {
+    MaxGenerations(u32),
+    // Stop once the best fitness hasn't improved by more than `min_delta`
+    // for `patience` consecutive generations.
+    FitnessPlateau { patience: u32, min_delta: f32 },
+    // Stop as soon as the best genome's summed squared error drops at or
+    // below this value.
+    TargetError(f32),
+}
+
+pub struct GaConfig {
+    // Initial guess the population is jittered from, e.g. a hand-tuned
+    // `generate_test_param_vec`-style matrix.
+    pub seed: ParameterMatrix,
+    pub resources: ResourceVector,
+    // Seed population inserted into each candidate FSM before rollout --
+    // without this every run stays at population 0 and fitness can't
+    // distinguish candidates.
+    pub seed_pop: u32,
+    pub population_size: usize,
+    pub tournament_size: usize,
+    pub mut_rate: f32,
+    pub mut_strength_start: f32,
+    pub mut_strength_end: f32,
+    pub stop: StopCriterion,
+}
+
+impl Default for GaConfig {
+    fn default() -> Self {
+        GaConfig {
+            seed: ParameterMatrix::default(),
+            resources: ResourceVector::default(),
+            seed_pop: 100,
+            population_size: 64,
+            tournament_size: 4,
+            mut_rate: 0.1,
+            mut_strength_start: 1.0,
+            mut_strength_end: 0.05,
+            stop: StopCriterion::MaxGenerations(200),
+        }
+    }
+}
+
+// One candidate solution: a full `ParameterMatrix` (14 params x
+// NUM_RESOURCES+1 coefficients, flattened as `rows`).
+#[derive(Clone)]
+struct Genome {
+    params: ParameterMatrix,
+}
+
+impl Genome {
+    fn jittered(seed: &ParameterMatrix, strength: f32, rng: &mut impl Rng) -> Self {
+        let mut rows = *seed.rows();
+        let normal = Normal::new(0.0, strength as f64).unwrap();
+        for row in rows.iter_mut() {
+            for entry in row.iter_mut() {
+                *entry += normal.sample(rng) as f32;
+            }
+        }
+        Genome {
+            params: ParameterMatrix::from_rows(rows),
+        }
+    }
+
+    fn crossover(a: &Genome, b: &Genome, rng: &mut impl Rng) -> Genome {
+        let pick = |x: f32, y: f32, rng: &mut impl Rng| if rng.gen_bool(0.5) { x } else { y };
+
+        let mut rows = *a.params.rows();
+        for (row, (row_a, row_b)) in rows
+            .iter_mut()
+            .zip(a.params.rows().iter().zip(b.params.rows().iter()))
+        {
+            for (entry, (x, y)) in row.iter_mut().zip(row_a.iter().zip(row_b.iter())) {
+                *entry = pick(*x, *y, rng);
+            }
+        }
+
+        Genome {
+            params: ParameterMatrix::from_rows(rows),
+        }
+    }
+
+    fn mutate(&mut self, mut_rate: f32, strength: f32, rng: &mut impl Rng) {
+        let normal = Normal::new(0.0, strength as f64).unwrap();
+        for row in self.params.rows_mut().iter_mut() {
+            for entry in row.iter_mut() {
+                if rng.gen_bool(mut_rate as f64) {
+                    *entry += normal.sample(rng) as f32;
+                }
+            }
+        }
+    }
+}
+
+// Run `params` forward through a deterministic FSM for `target.len()` steps
+// against `resources`, scoring `-\sum (sim - obs)^2` over both total and
+// mature population.
+fn fitness(
+    params: &ParameterMatrix,
+    resources: &ResourceVector,
+    target: &[PopObservation],
+    seed_pop: u32,
+) -> f32 {
+    let mut fsm: PlantStateMachine<GroundTruthStatePipe> = PlantStateMachine::default();
+    fsm.insert_seeds(seed_pop);
+    let mut error = 0.0_f32;
+    for obs in target {
+        fsm.step(params * resources);
+        error += (fsm.total_pop() as f32 - obs.total_pop).powi(2)
+            + (fsm.mature_pop() as f32 - obs.mature_pop).powi(2);
+    }
+    -error
+}
+
+fn tournament_select<'a>(
+    scored: &'a [(f32, Genome)],
+    size: usize,
+    rng: &mut impl Rng,
+) -> &'a Genome {
+    let mut best = &scored[rng.gen_range(0..scored.len())];
+    for _ in 1..size.max(1) {
+        let candidate = &scored[rng.gen_range(0..scored.len())];
+        if candidate.0 > best.0 {
+            best = candidate;
+        }
+    }
+    &best.1
+}
+
+// Evolve a `ParameterMatrix` to fit `target`, starting from `config.seed`
+// and following the same uniform-crossover / Gaussian-mutation shape as the
+// weights-matrix GA in `ga.rs`, but picking parents by tournament selection
+// (instead of `ga.rs`'s top-k truncation) and scoring against an FSM
+// rollout instead of a live cellular grid.
+pub fn calibrate(target: &[PopObservation], config: GaConfig) -> ParameterMatrix {
+    let mut rng = rand::thread_rng();
+
+    let population_size = config.population_size.max(1);
+    let mut population: Vec<Genome> = (0..population_size)
+        .map(|_| Genome::jittered(&config.seed, config.mut_strength_start, &mut rng))
+        .collect();
+
+    let mut best = population[0].clone();
+    let mut best_fitness = f32::NEG_INFINITY;
+    let mut plateau_count = 0u32;
+    let mut generation = 0u32;
+
+    loop {
+        let progress = match config.stop {
+            StopCriterion::MaxGenerations(max) => generation as f32 / max.max(1) as f32,
+            _ => 0.0,
+        };
+        let strength = config.mut_strength_start
+            + (config.mut_strength_end - config.mut_strength_start) * progress.min(1.0);
+
+        let scored: Vec<(f32, Genome)> = population
+            .par_iter()
+            .map(|genome| {
+                (
+                    fitness(&genome.params, &config.resources, target, config.seed_pop),
+                    genome.clone(),
+                )
+            })
+            .collect();
+
+        let (gen_best_fitness, gen_best) = scored
+            .iter()
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+            .map(|(f, g)| (*f, g.clone()))
+            .unwrap();
+
+        let improved = gen_best_fitness - best_fitness;
+        if gen_best_fitness > best_fitness {
+            best_fitness = gen_best_fitness;
+            best = gen_best;
+        }
+
+        let should_stop = match config.stop {
+            StopCriterion::MaxGenerations(max) => generation + 1 >= max,
+            StopCriterion::TargetError(target_error) => best_fitness >= -target_error,
+            StopCriterion::FitnessPlateau { patience, min_delta } => {
+                if improved > min_delta {
+                    plateau_count = 0;
+                } else {
+                    plateau_count += 1;
+                }
+                plateau_count >= patience
+            }
+        };
+
+        if should_stop {
+            break;
+        }
+
+        population = (0..population_size)
+            .map(|_| {
+                let a = tournament_select(&scored, config.tournament_size, &mut rng);
+                let b = tournament_select(&scored, config.tournament_size, &mut rng);
+                let mut child = Genome::crossover(a, b, &mut rng);
+                child.mutate(config.mut_rate, strength, &mut rng);
+                child
+            })
+            .collect();
+
+        generation += 1;
+    }
+
+    best.params
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn small_config() -> GaConfig {
+        GaConfig {
+            population_size: 16,
+            tournament_size: 3,
+            stop: StopCriterion::MaxGenerations(15),
+            ..GaConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_calibrate_fits_different_targets_differently() {
+        let low_target = vec![
+            PopObservation {
+                total_pop: 5.0,
+                mature_pop: 1.0,
+            };
+            20
+        ];
+        let high_target = vec![
+            PopObservation {
+                total_pop: 500.0,
+                mature_pop: 200.0,
+            };
+            20
+        ];
+
+        let low_fit = calibrate(&low_target, small_config());
+        let high_fit = calibrate(&high_target, small_config());
+
+        assert_ne!(
+            low_fit.rows(),
+            high_fit.rows(),
+            "calibrating against very different target trajectories should not converge to the same matrix"
+        );
+    }
+}
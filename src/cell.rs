@@ -1,4 +1,6 @@
 use egui::lerp;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use ultraviolet::{Mat4, Vec4};
 
 #[derive(Clone, Default)]
@@ -14,12 +16,138 @@ pub struct Cell {
 
 impl Cell {
     pub fn step(&mut self, mat: Mat4, dt: f32) {
-        // let rate = mat * self.density;
-        // let d_density_dt = rate * self.density * (Vec4::one() - self.density / self.resources);
-        // self.density += d_density_dt * dt;
+        let rate = mat * self.density;
+        let mut new_density = self.density;
 
-        let new_density = mat * self.density;
+        for i in 0..4 {
+            let r = rate.as_slice()[i];
+            let d = self.density.as_slice()[i];
+            let k = self.resources.as_slice()[i];
+
+            let d_density_dt = if k <= 0.0 {
+                // Zero resources is a hard carrying capacity of zero: any
+                // existing population can only die off, never grow.
+                -d
+            } else {
+                let logistic = r * d * (1.0 - d / k);
+                // Mortality proportional to how far density has overshot
+                // the available resources, so overshoot self-corrects
+                // instead of just being clamped away.
+                let overshoot = (d - k).max(0.0);
+                logistic - overshoot * overshoot
+            };
+
+            new_density.as_mut_slice()[i] = (d + d_density_dt * dt).clamp(0.0, 1.0);
+        }
+
+        self.density = new_density;
+    }
+
+    // Alternative nonlinear update: run `density` (and optionally `resources`)
+    // through a small feed-forward `Network` instead of a single linear `Mat4`.
+    pub fn step_network(&mut self, network: &Network, dt: f32) {
+        let mut input: Vec<f32> = self.density.as_slice().to_vec();
+        if network.use_resources {
+            input.extend_from_slice(self.resources.as_slice());
+        }
+
+        let output = network.forward(&input);
+        let new_density = Vec4::new(output[0], output[1], output[2], output[3]);
         self.density = lerp(self.density..=new_density, dt);
-        // self.density.clamp(Vec4::zero(), Vec4::one());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activation {
+    Tanh,
+    Relu,
+    Sigmoid,
+}
+
+impl Activation {
+    fn apply(self, x: f32) -> f32 {
+        match self {
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.max(0.0),
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+        }
+    }
+}
+
+// weights[out] holds the incoming weights for one output neuron, with the
+// bias appended as the last entry, so a layer going from `inputs` to
+// `outputs` neurons stores `outputs` rows of `inputs + 1` weights.
+#[derive(Clone, Serialize, Deserialize)]
+struct Layer {
+    weights: Vec<Vec<f32>>,
+}
+
+impl Layer {
+    fn random(inputs: usize, outputs: usize) -> Self {
+        let mut rng = rand::thread_rng();
+        Layer {
+            weights: (0..outputs)
+                .map(|_| (0..=inputs).map(|_| rng.gen_range(-1.0..1.0)).collect())
+                .collect(),
+        }
+    }
+
+    fn forward(&self, input: &[f32], activation: Activation) -> Vec<f32> {
+        self.weights
+            .iter()
+            .map(|row| {
+                let (weights, bias) = row.split_at(row.len() - 1);
+                let sum: f32 = weights.iter().zip(input).map(|(w, x)| w * x).sum();
+                activation.apply(sum + bias[0])
+            })
+            .collect()
+    }
+}
+
+// A small feed-forward network replacing the single `Mat4` transform:
+// `hidden_sizes` gives the width of each hidden layer, and the final layer
+// always produces the 4-wide output that becomes the new `density`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Network {
+    pub hidden_sizes: Vec<usize>,
+    pub activation: Activation,
+    pub use_resources: bool,
+    layers: Vec<Layer>,
+}
+
+impl Network {
+    pub fn new(hidden_sizes: Vec<usize>, activation: Activation, use_resources: bool) -> Self {
+        let input_size = if use_resources { 8 } else { 4 };
+
+        let mut layer_sizes = hidden_sizes.clone();
+        layer_sizes.push(4);
+
+        let mut layers = Vec::with_capacity(layer_sizes.len());
+        let mut prev_size = input_size;
+        for &size in &layer_sizes {
+            layers.push(Layer::random(prev_size, size));
+            prev_size = size;
+        }
+
+        Network {
+            hidden_sizes,
+            activation,
+            use_resources,
+            layers,
+        }
+    }
+
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let mut x = input.to_vec();
+        for layer in &self.layers {
+            x = layer.forward(&x, self.activation);
+        }
+        x
+    }
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network::new(vec![4, 9, 9, 4], Activation::Tanh, false)
     }
 }
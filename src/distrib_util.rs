@@ -1,31 +1,68 @@
-use std::f32::consts::E;
+use std::f32::consts::PI;
 
-const NORMAL_CDF_APPROX_CONST: f32 = 1.65451;
+// How precisely `integrate_gaussian` resolves a bucket's probability mass,
+// and a backstop against pathological recursion near machine precision.
+const GAUSSIAN_INTEGRATION_EPS: f32 = 1e-6;
+const ADAPTIVE_SIMPSON_MAX_DEPTH: u32 = 50;
 
-/**
- * Approximation of normal CDF.
- *
- * -1.65451 is a magic constant that has the lowest deviation from the actual normal CDF.
- */
-fn normal_cdf(mean: f32, std: f32, x: f32) -> f32 {
-    1.0 / (1.0 + E.powf((-NORMAL_CDF_APPROX_CONST / std) * (x - mean)))
+fn gaussian_pdf(mean: f32, std: f32, x: f32) -> f32 {
+    let z = (x - mean) / std;
+    (-0.5 * z * z).exp() / (std * (2.0 * PI).sqrt())
+}
+
+fn simpson_estimate(mean: f32, std: f32, a: f32, b: f32) -> f32 {
+    (b - a) / 6.0
+        * (gaussian_pdf(mean, std, a)
+            + 4.0 * gaussian_pdf(mean, std, (a + b) / 2.0)
+            + gaussian_pdf(mean, std, b))
+}
+
+fn adaptive_simpson_recurse(
+    mean: f32,
+    std: f32,
+    a: f32,
+    b: f32,
+    eps: f32,
+    whole: f32,
+    depth: u32,
+) -> f32 {
+    let m = (a + b) / 2.0;
+    let left = simpson_estimate(mean, std, a, m);
+    let right = simpson_estimate(mean, std, m, b);
+    let delta = left + right - whole;
+
+    if depth == 0 || delta.abs() < 15.0 * eps {
+        left + right + delta / 15.0
+    } else {
+        adaptive_simpson_recurse(mean, std, a, m, eps / 2.0, left, depth - 1)
+            + adaptive_simpson_recurse(mean, std, m, b, eps / 2.0, right, depth - 1)
+    }
 }
 
-fn normal_prob(mean: f32, std: f32, min: f32, max: f32) -> f32 {
-    normal_cdf(mean, std, max) - normal_cdf(mean, std, min)
+/**
+ * Integral of the Gaussian pdf (mean `mean`, standard deviation `std`) over
+ * [a, b], via recursive adaptive Simpson's rule refined until the
+ * Richardson error estimate drops below `eps`.
+ */
+fn integrate_gaussian(mean: f32, std: f32, a: f32, b: f32, eps: f32) -> f32 {
+    let whole = simpson_estimate(mean, std, a, b);
+    adaptive_simpson_recurse(mean, std, a, b, eps, whole, ADAPTIVE_SIMPSON_MAX_DEPTH)
 }
 
 fn get_pop_normal_distrib(pop_size: u32, age_std: f32) -> Vec<u32> {
-    // Find maximum negative Z where the PDF is less than 0.5 e.g. rounds down to zero
+    let bucket_mass = |age: i32| {
+        integrate_gaussian(
+            0.0,
+            age_std,
+            age as f32 - 0.5,
+            age as f32 + 0.5,
+            GAUSSIAN_INTEGRATION_EPS,
+        )
+    };
+
+    // Find the most negative age whose bucket count (integrated mass times pop_size) is still at least 0.5; the next age down rounds to zero
     let mut neg_age: i32 = 0;
-    while normal_prob(
-        0.0,
-        age_std,
-        ((neg_age - 1) as f32 + 0.5) / age_std,
-        (neg_age as f32 + 0.5) / age_std,
-    ) * pop_size as f32
-        >= 0.5
-    {
+    while bucket_mass(neg_age - 1) * pop_size as f32 >= 0.5 {
         neg_age -= 1;
     }
 
@@ -38,26 +75,68 @@ fn get_pop_normal_distrib(pop_size: u32, age_std: f32) -> Vec<u32> {
     } else {
         // Can construct something resembling a normal distribution
         (neg_age..=-neg_age)
-            .map(|age| {
-                (normal_prob(
-                    0.0,
-                    age_std,
-                    (age as f32 + 0.5) / age_std,
-                    ((age + 1) as f32 + 0.5) / age_std,
-                ) * pop_size as f32) as u32
-            })
+            .map(|age| (bucket_mass(age) * pop_size as f32).round() as u32)
             .collect()
     }
 }
 
+// An empirical cumulative distribution built from a sample of observations,
+// used to compare a simulated observable against an observed one via the
+// Kolmogorov-Smirnov statistic.
+pub struct Cdf {
+    sorted: Vec<f32>,
+}
+
+impl Cdf {
+    pub fn from_samples(samples: &[f32]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Cdf { sorted }
+    }
+
+    // F(x): fraction of samples <= x.
+    pub fn eval(&self, x: f32) -> f32 {
+        let count = self.sorted.partition_point(|&s| s <= x);
+        count as f32 / self.sorted.len() as f32
+    }
+}
+
+// Two-sample KS statistic D = max_x |F_a(x) - F_b(x)|. The statistic can
+// only change at a sample point from either distribution, so it suffices to
+// evaluate both CDFs there instead of scanning a continuous range.
+pub fn ks_statistic(a: &Cdf, b: &Cdf) -> f32 {
+    a.sorted
+        .iter()
+        .chain(b.sorted.iter())
+        .map(|&x| (a.eval(x) - b.eval(x)).abs())
+        .fold(0.0, f32::max)
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::distrib_util::*;
 
     fn calc_distrib_pop_minimum(std: f32) -> u32 {
-        let r = NORMAL_CDF_APPROX_CONST / (std * 2.0);
-        let min_pop = 0.5 * (E.powf(r) + 1.0) / (E.powf(r) - 1.0);
-        min_pop.ceil() as u32
+        // Smallest population for which the central bucket's count
+        // (integrated mass times population) reaches 0.5, i.e. no longer
+        // rounds down to zero.
+        let central_mass = integrate_gaussian(0.0, std, -0.5, 0.5, GAUSSIAN_INTEGRATION_EPS);
+        (0.5 / central_mass).ceil() as u32
+    }
+
+    #[test]
+    fn test_ks_statistic_identical_distributions_is_zero() {
+        let samples = [1.0, 2.0, 2.0, 3.0, 5.0, 8.0];
+        let a = Cdf::from_samples(&samples);
+        let b = Cdf::from_samples(&samples);
+        assert_eq!(ks_statistic(&a, &b), 0.0);
+    }
+
+    #[test]
+    fn test_ks_statistic_disjoint_distributions_is_one() {
+        let a = Cdf::from_samples(&[0.0, 0.0, 0.0]);
+        let b = Cdf::from_samples(&[10.0, 10.0, 10.0]);
+        assert_eq!(ks_statistic(&a, &b), 1.0);
     }
 
     const STD_TOO_BIG_STD_INCR: f32 = 2.0;
@@ -3,7 +3,7 @@ use crate::state_pipe::*;
 use crate::World;
 
 #[derive(Default)]
-struct PlantStateMachine<T: StatePipe> {
+pub(crate) struct PlantStateMachine<T: StatePipe> {
     inserted_seeds: u32,
     immature_seeds: T,
     mature_seeds: T,
@@ -18,6 +18,52 @@ struct PlantStateMachine<T: StatePipe> {
     snags: T,
 }
 
+// Extrapolated equilibrium populations from `run_to_steady_state`, and the
+// step at which convergence was detected.
+pub struct SteadyState {
+    pub step: u32,
+    pub total_pop: f32,
+    pub mature_pop: f32,
+}
+
+// Aitken's delta-squared extrapolation of the limit of a sequence from
+// three successive terms, or `None` if the second difference is too close
+// to zero to trust (the series isn't converging geometrically yet).
+fn aitken_accelerate(x0: f32, x1: f32, x2: f32, min_denom: f32) -> Option<f32> {
+    let denom = x2 - 2.0 * x1 + x0;
+    if denom.abs() < min_denom {
+        None
+    } else {
+        Some(x0 - (x1 - x0).powi(2) / denom)
+    }
+}
+
+impl<T: StatePipe + Default + Seedable> PlantStateMachine<T> {
+    // Reseed every pipe from a single world-level seed (each pipe gets its
+    // own derived sub-seed, so their draws don't correlate) so a given seed
+    // reproduces a run exactly.
+    pub(crate) fn new_seeded(seed: u64) -> Self {
+        let mut this = Self::default();
+        for (i, pipe) in [
+            &mut this.immature_seeds,
+            &mut this.mature_seeds,
+            &mut this.immature_plants,
+            &mut this.mature_plants,
+            &mut this.flowering_plants,
+            &mut this.flower_recovering_plants,
+            &mut this.dispersing_plants,
+            &mut this.disperse_recovering_plants,
+            &mut this.snags,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            pipe.reseed(seed ^ (i as u64).wrapping_mul(0x9E3779B97F4A7C15));
+        }
+        this
+    }
+}
+
 impl<T: StatePipe + Default> PlantStateMachine<T> {
     fn update_states(&mut self, param_vec: &SpecificParameterVector) {
         self.immature_seeds
@@ -43,7 +89,7 @@ impl<T: StatePipe + Default> PlantStateMachine<T> {
     // An unused variable in this function is a great indicator that the developer forgot
     // to connect something in the state machine!
     #[deny(unused_variables)]
-    fn step(&mut self, param_vec: SpecificParameterVector) {
+    pub(crate) fn step(&mut self, param_vec: SpecificParameterVector) {
         self.update_states(&param_vec);
 
         let matured_seeds = self.immature_seeds.loss();
@@ -52,9 +98,9 @@ impl<T: StatePipe + Default> PlantStateMachine<T> {
         let dead_plants = self.mature_plants.loss();
         let flowered_plants = self.flowering_plants.loss();
         let dispersed_plants = self.dispersing_plants.loss();
-        let total_seeds_dispersed = (self.dispersing_plants.pop() as f32
-            * param_vec.float_param(PlantFsmParams::DispersionRate))
-            as u32;
+        let total_seeds_dispersed = self
+            .dispersing_plants
+            .disperse(param_vec.float_param(PlantFsmParams::DispersionRate));
         let recovered_plants =
             self.flower_recovering_plants.loss() + self.disperse_recovering_plants.loss();
 
@@ -140,6 +186,53 @@ impl<T: StatePipe + Default> PlantStateMachine<T> {
     pub fn total_pop(&self) -> u32 {
         self.immature_pop() + self.mature_pop() + self.snag_pop()
     }
+
+    // Step forward under `params` until Aitken-accelerated estimates of
+    // `total_pop`/`mature_pop` stop changing by more than `tol` between
+    // consecutive steps, or `max_steps` is reached without converging.
+    pub fn run_to_steady_state(
+        &mut self,
+        params: SpecificParameterVector,
+        max_steps: u32,
+        tol: f32,
+    ) -> Option<SteadyState> {
+        const MIN_DENOM: f32 = 1e-6;
+
+        let mut history: Vec<(f32, f32)> = Vec::new();
+        let mut prev_accel: Option<(f32, f32)> = None;
+
+        for step in 0..max_steps {
+            self.step(params);
+            history.push((self.total_pop() as f32, self.mature_pop() as f32));
+
+            if history.len() < 3 {
+                continue;
+            }
+
+            let n = history.len();
+            let (t0, m0) = history[n - 3];
+            let (t1, m1) = history[n - 2];
+            let (t2, m2) = history[n - 1];
+
+            let accel = aitken_accelerate(t0, t1, t2, MIN_DENOM)
+                .zip(aitken_accelerate(m0, m1, m2, MIN_DENOM));
+
+            if let Some((total, mature)) = accel {
+                if let Some((prev_total, prev_mature)) = prev_accel {
+                    if (total - prev_total).abs() < tol && (mature - prev_mature).abs() < tol {
+                        return Some(SteadyState {
+                            step,
+                            total_pop: total,
+                            mature_pop: mature,
+                        });
+                    }
+                }
+                prev_accel = Some((total, mature));
+            }
+        }
+
+        None
+    }
 }
 
 #[cfg(test)]
@@ -200,4 +293,44 @@ pub mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_run_to_steady_state_converges_within_max_steps() {
+        let mut fsm: PlantStateMachine<GroundTruthStatePipe> = PlantStateMachine::default();
+        let params = generate_test_param_vec();
+
+        fsm.insert_seeds(FSM_TEST_START_POP);
+
+        let steady_state = fsm.run_to_steady_state(params, 500, 0.01);
+
+        assert!(
+            steady_state.is_some(),
+            "deterministic FSM should settle into steady state within 500 steps"
+        );
+        assert!(steady_state.unwrap().step < 500);
+    }
+
+    #[test]
+    fn test_stochastic_pipe_reproducible_by_seed() {
+        let params = generate_test_param_vec();
+
+        let run = |seed: u64| -> Vec<u32> {
+            let mut fsm: PlantStateMachine<StochasticStatePipe> =
+                PlantStateMachine::new_seeded(seed);
+            fsm.insert_seeds(FSM_TEST_START_POP);
+            (0..50)
+                .map(|_| {
+                    fsm.step(params);
+                    fsm.total_pop()
+                })
+                .collect()
+        };
+
+        assert_eq!(run(1234), run(1234), "same seed should reproduce exactly");
+        assert_ne!(
+            run(1234),
+            run(5678),
+            "different seeds should (almost certainly) diverge"
+        );
+    }
 }
@@ -0,0 +1,214 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use rayon::prelude::*;
+use ultraviolet::{Mat4, Vec4};
+
+use crate::cell::Cell;
+use crate::random_vec4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FitnessMode {
+    // Reward pattern formation: maximize the spatial variance of density.
+    Variance,
+    // Reward stability: minimize drift of the mean density from a target.
+    Stability,
+}
+
+impl Default for FitnessMode {
+    fn default() -> Self {
+        FitnessMode::Variance
+    }
+}
+
+// One candidate solution: the weights matrix plus (optionally evolved)
+// diffuse strength, the two knobs a user would otherwise hand-tune via the
+// TreeGro window.
+#[derive(Clone)]
+pub struct Genome {
+    pub matrix: Mat4,
+    pub diffuse_strength: Vec4,
+}
+
+impl Genome {
+    fn random() -> Self {
+        Genome {
+            matrix: Mat4::new(
+                random_vec4() * 2.0 - Vec4::one(),
+                random_vec4() * 2.0 - Vec4::one(),
+                random_vec4() * 2.0 - Vec4::one(),
+                random_vec4() * 2.0 - Vec4::one(),
+            ),
+            diffuse_strength: random_vec4(),
+        }
+    }
+
+    fn crossover(a: &Genome, b: &Genome, rng: &mut impl Rng) -> Genome {
+        let pick = |x: f32, y: f32, rng: &mut impl Rng| if rng.gen_bool(0.5) { x } else { y };
+
+        let mut matrix = Mat4::identity();
+        for (entry, (x, y)) in matrix
+            .as_mut_slice()
+            .iter_mut()
+            .zip(a.matrix.as_slice().iter().zip(b.matrix.as_slice().iter()))
+        {
+            *entry = pick(*x, *y, rng);
+        }
+
+        let diffuse_strength = Vec4::new(
+            pick(a.diffuse_strength.x, b.diffuse_strength.x, rng),
+            pick(a.diffuse_strength.y, b.diffuse_strength.y, rng),
+            pick(a.diffuse_strength.z, b.diffuse_strength.z, rng),
+            pick(a.diffuse_strength.w, b.diffuse_strength.w, rng),
+        );
+
+        Genome {
+            matrix,
+            diffuse_strength,
+        }
+    }
+
+    fn mutate(&mut self, mut_rate: f32, mut_strength: f32, rng: &mut impl Rng) {
+        let normal = Normal::new(0.0, mut_strength as f64).unwrap();
+        let mut jitter = |x: &mut f32| {
+            if rng.gen_bool(mut_rate as f64) {
+                *x += normal.sample(rng) as f32;
+            }
+        };
+
+        for entry in self.matrix.as_mut_slice() {
+            jitter(entry);
+        }
+        for entry in self.diffuse_strength.as_mut_slice() {
+            jitter(entry);
+        }
+    }
+}
+
+pub struct GaConfig {
+    pub population_size: usize,
+    pub keep_top_k: usize,
+    pub generations_per_batch: u32,
+    pub ticks_per_eval: u32,
+    pub mut_rate: f32,
+    pub mut_strength: f32,
+    pub fitness_mode: FitnessMode,
+    pub target_mean: f32,
+}
+
+impl Default for GaConfig {
+    fn default() -> Self {
+        GaConfig {
+            population_size: 32,
+            keep_top_k: 8,
+            generations_per_batch: 1,
+            ticks_per_eval: 20,
+            mut_rate: 0.1,
+            mut_strength: 0.1,
+            fitness_mode: FitnessMode::Variance,
+            target_mean: 0.5,
+        }
+    }
+}
+
+pub struct GaState {
+    pub config: GaConfig,
+    population: Vec<Genome>,
+    pub best_fitness: f32,
+    // The actual best-ever genome, tracked separately from `best_fitness`.
+    // The GA isn't elitist -- each generation's population is entirely new
+    // crossover/mutation children -- so a batch's fitness can regress and
+    // `run_batch` must still hand back this persisted genome rather than
+    // whatever the current population happens to contain.
+    best_genome: Option<Genome>,
+}
+
+impl Default for GaState {
+    fn default() -> Self {
+        GaState {
+            config: GaConfig::default(),
+            population: Vec::new(),
+            best_fitness: f32::NEG_INFINITY,
+            best_genome: None,
+        }
+    }
+}
+
+fn fitness(cells: &[Cell], mode: FitnessMode, target_mean: f32) -> f32 {
+    let n = cells.len() as f32;
+    let mean = cells.iter().map(|c| c.density).fold(Vec4::zero(), |a, b| a + b) / n;
+    match mode {
+        FitnessMode::Variance => {
+            cells
+                .iter()
+                .map(|c| (c.density - mean).mag_sq())
+                .sum::<f32>()
+                / n
+        }
+        FitnessMode::Stability => {
+            let mean_mag = (mean.x + mean.y + mean.z + mean.w) / 4.0;
+            -(mean_mag - target_mean).abs()
+        }
+    }
+}
+
+impl GaState {
+    // Evaluate the given genome by cloning `cells` and running `ticks_per_eval`
+    // ticks of the same update `Cell::step` uses, then scoring the
+    // result with the configured fitness mode.
+    fn evaluate(&self, genome: &Genome, cells: &[Cell], dt: f32) -> f32 {
+        let mut candidate = cells.to_vec();
+        for _ in 0..self.config.ticks_per_eval {
+            for cell in &mut candidate {
+                cell.step(genome.matrix, dt);
+            }
+        }
+        fitness(&candidate, self.config.fitness_mode, self.config.target_mean)
+    }
+
+    // Run `generations_per_batch` generations of selection/crossover/mutation
+    // against `cells`, returning the best genome found across all calls so
+    // far (not just this batch, since the GA isn't elitist and a batch's
+    // fitness can regress).
+    pub fn run_batch(&mut self, cells: &[Cell], dt: f32) -> Genome {
+        if self.population.len() != self.config.population_size {
+            self.population = (0..self.config.population_size)
+                .map(|_| Genome::random())
+                .collect();
+        }
+
+        for _ in 0..self.config.generations_per_batch {
+            let mut scored: Vec<(f32, Genome)> = self
+                .population
+                .par_iter()
+                .map(|genome| (self.evaluate(genome, cells, dt), genome.clone()))
+                .collect();
+            scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+            if scored[0].0 > self.best_fitness {
+                self.best_fitness = scored[0].0;
+                self.best_genome = Some(scored[0].1.clone());
+            }
+
+            let survivors: Vec<Genome> = scored
+                .into_iter()
+                .take(self.config.keep_top_k.max(1))
+                .map(|(_, genome)| genome)
+                .collect();
+
+            let mut rng = rand::thread_rng();
+            self.population = (0..self.config.population_size)
+                .map(|_| {
+                    let a = &survivors[rng.gen_range(0..survivors.len())];
+                    let b = &survivors[rng.gen_range(0..survivors.len())];
+                    let mut child = Genome::crossover(a, b, &mut rng);
+                    child.mutate(self.config.mut_rate, self.config.mut_strength, &mut rng);
+                    child
+                })
+                .collect();
+        }
+
+        self.best_genome
+            .clone()
+            .unwrap_or_else(|| self.population[0].clone())
+    }
+}
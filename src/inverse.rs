@@ -0,0 +1,128 @@
+use rand::Rng;
+use rayon::prelude::*;
+
+pub use crate::distrib_util::Cdf;
+use crate::distrib_util::ks_statistic;
+use crate::fsm::PlantStateMachine;
+use crate::param::{ParamColumnVector, PlantFsmParams, SpecificParameterVector};
+use crate::state_pipe::StochasticStatePipe;
+
+const TOTAL_PARAMS: usize = PlantFsmParams::TotalParams as usize;
+
+// Which scalar observable from a simulated run is compared against the
+// observed distribution.
+#[derive(Clone, Copy)]
+pub enum Observable {
+    TotalPop,
+    MaturePop,
+    SnagPop,
+}
+
+impl Observable {
+    fn sample(self, fsm: &PlantStateMachine<StochasticStatePipe>) -> f32 {
+        match self {
+            Observable::TotalPop => fsm.total_pop() as f32,
+            Observable::MaturePop => fsm.mature_pop() as f32,
+            Observable::SnagPop => fsm.snag_pop() as f32,
+        }
+    }
+}
+
+pub struct SearchSpace {
+    // Inclusive (min, max) bounds to sample each `PlantFsmParams` column
+    // from, indexed the same way as `ParamColumnVector`.
+    pub ranges: [(f32, f32); TOTAL_PARAMS],
+    pub candidates: usize,
+    pub sim_steps: u32,
+    // Seed population inserted into each candidate FSM before rollout --
+    // without this every run stays at population 0 and every candidate's
+    // CDF degenerates to the same all-zero sample.
+    pub seed_pop: u32,
+    pub observable: Observable,
+}
+
+fn random_candidate(search: &SearchSpace, rng: &mut impl Rng) -> SpecificParameterVector {
+    let mut columns: ParamColumnVector = [0.0; TOTAL_PARAMS];
+    for (col, &(lo, hi)) in columns.iter_mut().zip(search.ranges.iter()) {
+        *col = rng.gen_range(lo..=hi);
+    }
+    SpecificParameterVector::from_raw(columns)
+}
+
+// Run the stochastic FSM for `search.sim_steps` steps under `params`, once
+// per seed in `0..seeds`, and build the empirical CDF of the configured
+// observable across the resulting ensemble.
+fn simulate_cdf(params: SpecificParameterVector, search: &SearchSpace, seeds: usize) -> Cdf {
+    let samples: Vec<f32> = (0..seeds)
+        .into_par_iter()
+        .map(|seed| {
+            let mut fsm: PlantStateMachine<StochasticStatePipe> =
+                PlantStateMachine::new_seeded(seed as u64);
+            fsm.insert_seeds(search.seed_pop);
+            for _ in 0..search.sim_steps {
+                fsm.step(params);
+            }
+            search.observable.sample(&fsm)
+        })
+        .collect();
+    Cdf::from_samples(&samples)
+}
+
+// Monte Carlo inverse modeling: randomly sample `search.candidates`
+// parameter vectors from `search.ranges`, score each by the two-sample KS
+// statistic between its simulated-outcome CDF (across `seeds` independent
+// RNG seeds) and `observed`, and return every candidate whose statistic
+// falls at or below `threshold` -- a plausible region rather than a single
+// point estimate.
+pub fn fit_by_ks(
+    observed: &Cdf,
+    search: SearchSpace,
+    seeds: usize,
+    threshold: f32,
+) -> Vec<(SpecificParameterVector, f32)> {
+    let mut rng = rand::thread_rng();
+    let candidates: Vec<SpecificParameterVector> = (0..search.candidates)
+        .map(|_| random_candidate(&search, &mut rng))
+        .collect();
+
+    candidates
+        .into_par_iter()
+        .map(|params| {
+            let d = ks_statistic(observed, &simulate_cdf(params, &search, seeds));
+            (params, d)
+        })
+        .filter(|(_, d)| *d <= threshold)
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_by_ks_distinguishes_candidates() {
+        let observed = Cdf::from_samples(&[50.0; 20]);
+
+        let mut ranges = [(0.5, 20.0); TOTAL_PARAMS];
+        // A success ratio above 1 would make more plants succeed at
+        // flowering than flowered.
+        ranges[PlantFsmParams::FloweringSuccessRatio as usize] = (0.0, 1.0);
+
+        let search = SearchSpace {
+            ranges,
+            candidates: 8,
+            sim_steps: 30,
+            seed_pop: 50,
+            observable: Observable::TotalPop,
+        };
+
+        let results = fit_by_ks(&observed, search, 4, 1.0);
+
+        assert!(!results.is_empty());
+        let first_d = results[0].1;
+        assert!(
+            results.iter().any(|(_, d)| (d - first_d).abs() > 1e-6),
+            "all candidates scored identically -- population is probably stuck at zero again"
+        );
+    }
+}
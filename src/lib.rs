@@ -4,11 +4,19 @@ use egui::emath::Numeric as Num;
 use std::ops::RangeInclusive;
 use ultraviolet::Vec4;
 
+pub mod automaton;
+pub mod calibrate;
 pub mod cell;
+mod distrib_util;
 mod fsm;
+pub mod ga;
 mod gui;
+pub mod inverse;
 mod mainloop;
 pub mod param;
+pub mod psd;
+mod recorder;
+mod state_pipe;
 mod world;
 
 pub use mainloop::{mainloop, App, HEIGHT, WIDTH};
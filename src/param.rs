@@ -1,11 +1,14 @@
 use std::ops::Mul;
 
+use serde::{Deserialize, Serialize};
+
 use crate::NUM_RESOURCES;
 
 pub type ParamType = f32;
 pub type ParamRowVector = [ParamType; NUM_RESOURCES + 1];
 pub type ParamColumnVector = [ParamType; PlantFsmParams::TotalParams as usize];
 
+#[derive(Clone)]
 pub struct ParameterMatrix {
     rows: [ParamRowVector; PlantFsmParams::TotalParams as usize],
 }
@@ -26,6 +29,18 @@ impl ParameterMatrix {
     }
 
     pub fn map_offsets(&mut self, values: ParamColumnVector) {}
+
+    pub fn from_rows(rows: [ParamRowVector; PlantFsmParams::TotalParams as usize]) -> Self {
+        ParameterMatrix { rows }
+    }
+
+    pub fn rows(&self) -> &[ParamRowVector; PlantFsmParams::TotalParams as usize] {
+        &self.rows
+    }
+
+    pub fn rows_mut(&mut self) -> &mut [ParamRowVector; PlantFsmParams::TotalParams as usize] {
+        &mut self.rows
+    }
 }
 
 impl Mul<&ResourceVector> for &ParameterMatrix {
@@ -45,6 +60,7 @@ impl Mul<&ResourceVector> for &ParameterMatrix {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct ResourceVector {
     columns: [f32; NUM_RESOURCES as usize + 1],
 }
@@ -0,0 +1,219 @@
+use std::f32::consts::PI;
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DetrendMethod {
+    Midpoint,
+    Mean,
+    LinearFit,
+}
+
+impl Default for DetrendMethod {
+    fn default() -> Self {
+        DetrendMethod::Mean
+    }
+}
+
+fn detrend(segment: &mut [f32], method: DetrendMethod) {
+    match method {
+        DetrendMethod::Midpoint => {
+            let mid = segment[segment.len() / 2];
+            for v in segment.iter_mut() {
+                *v -= mid;
+            }
+        }
+        DetrendMethod::Mean => {
+            let mean = segment.iter().sum::<f32>() / segment.len() as f32;
+            for v in segment.iter_mut() {
+                *v -= mean;
+            }
+        }
+        DetrendMethod::LinearFit => {
+            let n = segment.len() as f32;
+            let x_mean = (n - 1.0) / 2.0;
+            let y_mean = segment.iter().sum::<f32>() / n;
+
+            let mut num = 0.0;
+            let mut den = 0.0;
+            for (i, y) in segment.iter().enumerate() {
+                let x = i as f32 - x_mean;
+                num += x * (y - y_mean);
+                den += x * x;
+            }
+            let slope = if den.abs() < f32::EPSILON { 0.0 } else { num / den };
+            let intercept = y_mean - slope * x_mean;
+
+            for (i, y) in segment.iter_mut().enumerate() {
+                *y -= slope * (i as f32) + intercept;
+            }
+        }
+    }
+}
+
+fn hann_window(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / (len as f32 - 1.0)).cos())
+        .collect()
+}
+
+// One resolution level of the Welch cascade: the series that fed it has
+// already been decimated by 2^decimation relative to the original snapshot.
+pub struct Stage {
+    pub decimation: u32,
+    // Frequency, in cycles per original (undecimated) sample.
+    pub freqs: Vec<f32>,
+    pub psd: Vec<f32>,
+    pub count: u32,
+}
+
+pub struct WelchConfig {
+    // Segment length is 2^segment_log2_len, with 50% overlap between segments.
+    pub segment_log2_len: u32,
+    pub detrend: DetrendMethod,
+    // A stage whose averaging count falls at or below this is dropped.
+    pub min_count: u32,
+    // EMA blend factor across successive calls: negative gives a constant
+    // (time-based) smoothing factor of |alpha|; non-negative blends the n-th
+    // update in with weight 1/(n + alpha), so the effective averaging window
+    // grows by one each update (constant-count-like).
+    pub alpha: f32,
+}
+
+impl Default for WelchConfig {
+    fn default() -> Self {
+        WelchConfig {
+            segment_log2_len: 5,
+            detrend: DetrendMethod::Mean,
+            min_count: 1,
+            alpha: 0.0,
+        }
+    }
+}
+
+fn periodogram(segment: &[f32], window: &[f32], planner: &mut FftPlanner<f32>) -> Vec<f32> {
+    let len = segment.len();
+    let fft = planner.plan_fft_forward(len);
+
+    let mut buf: Vec<Complex<f32>> = segment
+        .iter()
+        .zip(window)
+        .map(|(s, w)| Complex::new(s * w, 0.0))
+        .collect();
+    fft.process(&mut buf);
+
+    let window_power: f32 = window.iter().map(|w| w * w).sum();
+    buf[..len / 2 + 1]
+        .iter()
+        .map(|c| c.norm_sqr() / window_power)
+        .collect()
+}
+
+fn welch_stage(
+    series: &[f32],
+    decimation: u32,
+    config: &WelchConfig,
+    planner: &mut FftPlanner<f32>,
+) -> Option<Stage> {
+    let seg_len = 1usize << config.segment_log2_len;
+    if series.len() < seg_len {
+        return None;
+    }
+
+    let window = hann_window(seg_len);
+    let step = seg_len / 2;
+
+    let mut sum = vec![0.0f32; seg_len / 2 + 1];
+    let mut count = 0u32;
+    let mut start = 0;
+    while start + seg_len <= series.len() {
+        let mut segment = series[start..start + seg_len].to_vec();
+        detrend(&mut segment, config.detrend);
+        let p = periodogram(&segment, &window, planner);
+        for (s, v) in sum.iter_mut().zip(p.iter()) {
+            *s += v;
+        }
+        count += 1;
+        start += step;
+    }
+
+    if count == 0 {
+        return None;
+    }
+    for s in sum.iter_mut() {
+        *s /= count as f32;
+    }
+
+    let scale = (1u32 << decimation) as f32;
+    let freqs = (0..sum.len())
+        .map(|i| (i as f32 / seg_len as f32) / scale)
+        .collect();
+
+    Some(Stage {
+        decimation,
+        freqs,
+        psd: sum,
+        count,
+    })
+}
+
+// Decimate-then-segment cascade: each successive stage halves the series
+// (accumulating more averages at low frequency over a longer effective
+// window), stopping once a stage's averaging count falls at or below
+// `config.min_count` or there isn't enough data left for one segment.
+pub fn welch_cascade(series: &[f32], config: &WelchConfig) -> Vec<Stage> {
+    let mut planner = FftPlanner::<f32>::new();
+    let mut stages = Vec::new();
+    let mut current = series.to_vec();
+    let mut decimation = 0;
+
+    loop {
+        match welch_stage(&current, decimation, config, &mut planner) {
+            Some(stage) if stage.count > config.min_count => stages.push(stage),
+            _ => break,
+        }
+
+        current = current.iter().step_by(2).copied().collect();
+        decimation += 1;
+        if current.len() < (1usize << config.segment_log2_len) {
+            break;
+        }
+    }
+
+    stages
+}
+
+// Exponentially-averaged PSD for one channel, carried across successive plot
+// updates so the displayed curve smooths over time instead of jittering with
+// every new snapshot sample.
+#[derive(Default)]
+pub struct ChannelEma {
+    stages: Vec<(Vec<f32>, u32)>,
+}
+
+impl ChannelEma {
+    pub fn update(&mut self, stages: &[Stage], alpha: f32) -> Vec<(Vec<f32>, Vec<f32>)> {
+        if self.stages.len() != stages.len() {
+            self.stages = stages.iter().map(|s| (s.psd.clone(), 1)).collect();
+        } else {
+            for ((ema, count), stage) in self.stages.iter_mut().zip(stages) {
+                let weight = if alpha < 0.0 {
+                    -alpha
+                } else {
+                    *count += 1;
+                    1.0 / (*count as f32 + alpha)
+                };
+                for (e, v) in ema.iter_mut().zip(stage.psd.iter()) {
+                    *e = *e * (1.0 - weight) + v * weight;
+                }
+            }
+        }
+
+        stages
+            .iter()
+            .zip(self.stages.iter())
+            .map(|(stage, (ema, _))| (stage.freqs.clone(), ema.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs::File;
+use std::io::{self, Write};
+
+// Streams rendered frames to disk as a raw Y4M (YUV4MPEG2) stream instead of
+// keeping them in memory, so long recordings stay cheap. Y4M is understood
+// directly by ffmpeg and most Rust video encoders, so recordings can be
+// losslessly converted to any video format afterward.
+#[derive(Default)]
+pub struct Y4mRecorder {
+    file: Option<File>,
+    size: (u32, u32),
+}
+
+impl Y4mRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.file.is_some()
+    }
+
+    pub fn start(&mut self, path: &str, size: (u32, u32), framerate: u32) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", size.0, size.1, framerate)?;
+        self.file = Some(file);
+        self.size = size;
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.file = None;
+    }
+
+    // `rgba` is the same packed RGBA8 buffer `World::update` writes into the
+    // pixels framebuffer; only the RGB channels are kept, converted to planar
+    // YUV (BT.601, full range) to match Y4M's C444 colorspace.
+    pub fn write_frame(&mut self, rgba: &[u8]) -> io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+
+        let num_pixels = (self.size.0 * self.size.1) as usize;
+        let mut y_plane = vec![0u8; num_pixels];
+        let mut u_plane = vec![0u8; num_pixels];
+        let mut v_plane = vec![0u8; num_pixels];
+
+        for (i, pixel) in rgba.chunks_exact(4).enumerate().take(num_pixels) {
+            let (r, g, b) = (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+            y_plane[i] = (0.299 * r + 0.587 * g + 0.114 * b).round().clamp(0.0, 255.0) as u8;
+            u_plane[i] = (-0.169 * r - 0.331 * g + 0.5 * b + 128.0)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+            v_plane[i] = (0.5 * r - 0.419 * g - 0.081 * b + 128.0)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+
+        file.write_all(b"FRAME\n")?;
+        file.write_all(&y_plane)?;
+        file.write_all(&u_plane)?;
+        file.write_all(&v_plane)?;
+        Ok(())
+    }
+}
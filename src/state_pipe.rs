@@ -1,12 +1,29 @@
 use std::collections::VecDeque;
 use std::f32::EPSILON;
 
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use rand_pcg::Pcg64;
+
 pub trait StatePipe: Default {
     fn loss(&self) -> u32;
     fn pop(&self) -> u32;
     fn step(&mut self, gain: u32);
     fn set_period(&mut self, period: u32);
     fn cull_pop(&mut self, remove_percent: f32);
+
+    // Expected number of seeds dispersed at the given per-plant rate. The
+    // deterministic pipes floor the mean-field product; `StochasticStatePipe`
+    // overrides this to draw from Poisson(pop * rate) instead.
+    fn disperse(&mut self, rate: f32) -> u32 {
+        (self.pop() as f32 * rate) as u32
+    }
+}
+
+// Reseed a pipe from a world-level seed so a given seed reproduces a run
+// exactly. Only meaningful for pipes that carry their own RNG.
+pub trait Seedable {
+    fn reseed(&mut self, seed: u64);
 }
 
 #[derive(Default)]
@@ -155,6 +172,146 @@ impl StatePipe for GaussianStatePipe {
     }
 }
 
+// n <= this many individuals: just sum Bernoulli trials directly. Above it,
+// fall back to CDF inversion, which stays accurate without needing a full
+// rejection-based BTPE sampler for the population sizes this sim reaches.
+const BINOMIAL_DIRECT_THRESHOLD: u32 = 30;
+
+fn sample_binomial(rng: &mut Pcg64, n: u32, p: f32) -> u32 {
+    if n == 0 || p <= 0.0 {
+        return 0;
+    }
+    if p >= 1.0 {
+        return n;
+    }
+
+    if n <= BINOMIAL_DIRECT_THRESHOLD {
+        (0..n).filter(|_| rng.gen::<f32>() < p).count() as u32
+    } else {
+        let u: f64 = rng.gen();
+        let q = 1.0 - p as f64;
+        let mut term = q.powi(n as i32);
+        let mut cdf = term;
+        let mut k = 0u32;
+        while cdf < u && k < n {
+            k += 1;
+            term *= (n - k + 1) as f64 / k as f64 * (p as f64 / q);
+            cdf += term;
+        }
+        k
+    }
+}
+
+// Given `total` individuals of which `marked` are in the subgroup of
+// interest, draws `draws` individuals without replacement and returns how
+// many came from the marked subgroup (a Hypergeometric draw, sampled
+// sequentially since the population sizes this sim reaches stay small).
+// `pending_loss` is always a subset of `pop`, so culling both with
+// independent Binomial draws can leave `pending_loss > pop`; deriving the
+// pending-loss removal from the same draw as the population removal keeps
+// that invariant intact.
+fn sample_hypergeometric(rng: &mut Pcg64, total: u32, marked: u32, draws: u32) -> u32 {
+    let mut total = total;
+    let mut marked = marked;
+    let mut found = 0;
+    for _ in 0..draws.min(total) {
+        if rng.gen_range(0..total) < marked {
+            found += 1;
+            marked -= 1;
+        }
+        total -= 1;
+    }
+    found
+}
+
+const POISSON_DIRECT_THRESHOLD: f32 = 30.0;
+
+fn sample_poisson(rng: &mut Pcg64, lambda: f32) -> u32 {
+    if lambda <= 0.0 {
+        return 0;
+    }
+
+    if lambda < POISSON_DIRECT_THRESHOLD {
+        // Knuth's algorithm.
+        let limit = (-(lambda as f64)).exp();
+        let mut k = 0u32;
+        let mut p = 1.0f64;
+        loop {
+            k += 1;
+            p *= rng.gen::<f64>();
+            if p <= limit {
+                break;
+            }
+        }
+        k - 1
+    } else {
+        // Normal approximation for large lambda.
+        let normal = Normal::new(lambda as f64, (lambda as f64).sqrt()).unwrap();
+        normal.sample(rng).round().max(0.0) as u32
+    }
+}
+
+// Draws transitions stochastically instead of flooring expected values: a
+// cohort advances with probability `1/period` per step (a memoryless hazard,
+// unlike `GroundTruthStatePipe`'s exact age buckets), sampled as a Binomial
+// draw each tick so population counts stay integer and demographic noise
+// (including possible extinction) is visible.
+pub struct StochasticStatePipe {
+    period: u32,
+    pop: u32,
+    pending_loss: u32,
+    rng: Pcg64,
+}
+
+impl Default for StochasticStatePipe {
+    fn default() -> Self {
+        StochasticStatePipe {
+            period: 1,
+            pop: 0,
+            pending_loss: 0,
+            rng: Pcg64::seed_from_u64(0),
+        }
+    }
+}
+
+impl Seedable for StochasticStatePipe {
+    fn reseed(&mut self, seed: u64) {
+        self.rng = Pcg64::seed_from_u64(seed);
+    }
+}
+
+impl StatePipe for StochasticStatePipe {
+    fn loss(&self) -> u32 {
+        self.pending_loss
+    }
+
+    fn pop(&self) -> u32 {
+        self.pop
+    }
+
+    fn step(&mut self, gain: u32) {
+        self.pop = self.pop - self.pending_loss + gain;
+        let p = 1.0 / self.period.max(1) as f32;
+        self.pending_loss = sample_binomial(&mut self.rng, self.pop, p);
+    }
+
+    fn set_period(&mut self, period: u32) {
+        self.period = period;
+    }
+
+    fn cull_pop(&mut self, remove_percent: f32) {
+        let removed = sample_binomial(&mut self.rng, self.pop, remove_percent);
+        let removed_pending =
+            sample_hypergeometric(&mut self.rng, self.pop, self.pending_loss, removed);
+        self.pop -= removed;
+        self.pending_loss -= removed_pending;
+    }
+
+    fn disperse(&mut self, rate: f32) -> u32 {
+        sample_poisson(&mut self.rng, self.pop as f32 * rate)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::state_pipe::*;
@@ -1,33 +1,107 @@
 use bytemuck::Zeroable;
 use pixels::Pixels;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use ultraviolet::{Mat4, Vec4};
 
+use crate::automaton::{self, Automaton};
+use crate::cell::{Activation, Network};
+use crate::ga::{FitnessMode, GaState};
 use crate::param::ResourceVector;
+use crate::psd::{ChannelEma, DetrendMethod, WelchConfig};
+use crate::recorder::Y4mRecorder;
 use crate::{cell::Cell, *};
 
 pub const NUM_RESOURCES: usize = 4;
 
-#[derive(Default)]
+// Bump whenever a field is added/removed/renamed below so old preset files
+// can still be told apart from incompatible ones.
+pub const PRESET_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpdateMode {
+    Linear,
+    Network,
+    Automaton,
+}
+
+impl Default for UpdateMode {
+    fn default() -> Self {
+        UpdateMode::Linear
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
 pub struct World {
+    #[serde(skip)]
     cells: Vec<Cell>,
+    #[serde(skip)]
     cells_tmp: Vec<Cell>,
+    #[serde(skip)]
     running: bool,
+    #[serde(skip)]
     absolute_value: bool,
     time_delta: f32,
     ticks_per_frame: i16, // if negative, then frames per tick
+    #[serde(skip)]
     tick_timer: u8,
     matrix: Mat4,
     diffuse: f32,
     diffuse_enabled: bool,
     diffuse_strength: Vec4,
     pixels_size: (u32, u32),
+    #[serde(skip)]
     snapshot: Vec<Vec<Cell>>,
+    #[serde(skip)]
     snapshot_enabled: bool,
     pub resources: ResourceVector,
+    #[serde(skip)]
+    ga: GaState,
+    update_mode: UpdateMode,
+    network: Network,
+    #[serde(skip)]
+    network_topology_text: String,
+    #[serde(skip)]
+    preset_name: String,
+    #[serde(skip)]
+    psd_enabled: bool,
+    #[serde(skip)]
+    psd_config: WelchConfig,
+    #[serde(skip)]
+    psd_ema: Vec<ChannelEma>,
+    #[serde(skip)]
+    recorder: Y4mRecorder,
+    #[serde(skip)]
+    record_path: String,
+    // Exponential moving average of the real wall-clock time between
+    // `update()` calls, used to pick a Y4M recording's framerate -- this is
+    // how often frames are actually written, which has no fixed relationship
+    // to `time_delta` (the simulation's per-tick dt) or `ticks_per_frame`.
+    #[serde(skip)]
+    last_update_instant: Option<std::time::Instant>,
+    #[serde(skip)]
+    avg_frame_secs: f32,
+    #[serde(skip)]
+    automaton: Automaton,
+    #[serde(skip)]
+    ca_num_states: u32,
+    #[serde(skip)]
+    ca_grid: Vec<automaton::State>,
+    #[serde(skip)]
+    ca_grid_tmp: Vec<automaton::State>,
+    #[serde(skip)]
+    rules_text: String,
 }
 
 impl World {
     pub fn new() -> Self {
+        let network = Network::default();
+        let network_topology_text = network
+            .hidden_sizes
+            .iter()
+            .map(|size| size.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
         let mut this = Self {
             pixels_size: (WIDTH, HEIGHT),
             ticks_per_frame: 1,
@@ -35,12 +109,85 @@ impl World {
             matrix: Mat4::zeroed(),
             diffuse: 0.2,
             diffuse_strength: Vec4::one(),
+            network,
+            network_topology_text,
+            ca_num_states: 2,
             ..Self::default()
         };
         this.randomize();
         this
     }
 
+    fn presets_dir() -> &'static std::path::Path {
+        std::path::Path::new("presets")
+    }
+
+    fn list_presets() -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(Self::presets_dir()) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension()? == "json")
+                    .then(|| path.file_stem()?.to_str().map(str::to_owned))
+                    .flatten()
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn save_preset(&self, name: &str) -> std::io::Result<()> {
+        #[derive(Serialize)]
+        struct PresetFile<'a> {
+            schema_version: u32,
+            world: &'a World,
+        }
+
+        std::fs::create_dir_all(Self::presets_dir())?;
+        let file = PresetFile {
+            schema_version: PRESET_SCHEMA_VERSION,
+            world: self,
+        };
+        let json = serde_json::to_string_pretty(&file)?;
+        std::fs::write(Self::presets_dir().join(format!("{name}.json")), json)
+    }
+
+    fn load_preset(name: &str) -> std::io::Result<Self> {
+        #[derive(Deserialize)]
+        struct PresetFile {
+            schema_version: u32,
+            world: World,
+        }
+
+        let json = std::fs::read_to_string(Self::presets_dir().join(format!("{name}.json")))?;
+        let file: PresetFile = serde_json::from_str(&json)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        if file.schema_version != PRESET_SCHEMA_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "preset schema version {} is incompatible with {}",
+                    file.schema_version, PRESET_SCHEMA_VERSION
+                ),
+            ));
+        }
+
+        let mut world = file.world;
+        world.network_topology_text = world
+            .network
+            .hidden_sizes
+            .iter()
+            .map(|size| size.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        world.preset_name = name.to_owned();
+        world.randomize();
+        Ok(world)
+    }
+
     fn randomize(&mut self) {
         let num_cells = self.pixels_size.0 * self.pixels_size.1;
         self.cells.clear();
@@ -48,43 +195,104 @@ impl World {
             resources: Vec4::broadcast(0.5),
             density: random_vec4(),
         });
+        self.ca_grid.clear();
     }
 
-    fn idx(&self, x: i32, y: i32) -> Option<usize> {
-        let i = (x + (self.pixels_size.0 as i32) * y) as isize;
-        if 0 <= i && i < (self.cells.len() as isize) {
-            Some(i as usize)
-        } else {
-            None
-        }
-    }
-
+    // Separable box blur: a horizontal pass followed by a vertical pass, each
+    // parallelized over rows with rayon. This turns the O(9N) 3x3 gather into
+    // two O(3N) passes while keeping the same clamping/renormalization
+    // semantics as the single-pass version (divide by the count of in-bounds
+    // neighbors along that axis). Both passes only average -- the lerp
+    // against the pre-diffuse density is applied exactly once, after the
+    // vertical pass, so `amount < 1` isn't compounded across both passes.
     fn diffuse_pass(&mut self) {
         let amount = (self.diffuse * self.diffuse_strength).clamped(Vec4::zero(), Vec4::one());
+        let width = self.pixels_size.0 as usize;
+        let height = self.pixels_size.1 as usize;
+
+        let original = self.cells.clone();
+
+        // Horizontal pass: average each row against its left/right neighbors.
         self.cells_tmp.clone_from(&self.cells);
+        let cells = &self.cells;
+        self.cells_tmp
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row_tmp)| {
+                let row = &cells[y * width..y * width + width];
+                for x in 0..width {
+                    let mut sum = row[x].density;
+                    let mut total = 1;
+                    if x > 0 {
+                        sum += row[x - 1].density;
+                        total += 1;
+                    }
+                    if x + 1 < width {
+                        sum += row[x + 1].density;
+                        total += 1;
+                    }
+                    row_tmp[x].density = sum / (total as f32);
+                }
+            });
+        std::mem::swap(&mut self.cells, &mut self.cells_tmp);
 
-        // really inefficient
-        for x in 0..(self.pixels_size.0 as i32) {
-            for y in 0..(self.pixels_size.1 as i32) {
-                let mut sum = Vec4::zero();
-                let mut total = 0;
-                for dx in -1..=1i32 {
-                    for dy in -1..=1i32 {
-                        if let Some(i) = self.idx(x + dx, y + dy) {
-                            total += 1;
-                            sum += self.cells[i].density;
-                        }
+        // Vertical pass: average the horizontally-averaged buffer against the
+        // rows above/below it, then lerp the original (pre-diffuse) density
+        // toward that fully-averaged result.
+        self.cells_tmp.clone_from(&self.cells);
+        let cells = &self.cells;
+        self.cells_tmp
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row_tmp)| {
+                let row = &cells[y * width..y * width + width];
+                let above = (y > 0).then(|| &cells[(y - 1) * width..(y - 1) * width + width]);
+                let below =
+                    (y + 1 < height).then(|| &cells[(y + 1) * width..(y + 1) * width + width]);
+                let original_row = &original[y * width..y * width + width];
+                for x in 0..width {
+                    let mut sum = row[x].density;
+                    let mut total = 1;
+                    if let Some(above) = above {
+                        sum += above[x].density;
+                        total += 1;
                     }
+                    if let Some(below) = below {
+                        sum += below[x].density;
+                        total += 1;
+                    }
+                    let average = sum / (total as f32);
+                    row_tmp[x].density = lerp_vec4(original_row[x].density, average, amount);
                 }
-                let i = self.idx(x, y).unwrap();
-                let current = self.cells[i].density;
-                let average = sum / (total as f32);
-                let new_density = lerp_vec4(current, average, amount);
-                self.cells_tmp[i].density = new_density;
-            }
+            });
+        std::mem::swap(&mut self.cells, &mut self.cells_tmp);
+    }
+
+    // Quantize density.x into the discrete `State` grid (only when the grid
+    // is empty or its size no longer matches the cells), run one tick of the
+    // rule-based automaton, then dequantize the result back into density
+    // (broadcast to all four channels so the CA renders as greyscale).
+    fn step_automaton(&mut self) {
+        let width = self.pixels_size.0 as usize;
+        let height = self.pixels_size.1 as usize;
+        let num_cells = width * height;
+
+        if self.ca_grid.len() != num_cells {
+            self.ca_grid = self
+                .cells
+                .iter()
+                .map(|cell| automaton::quantize(cell.density.x, self.ca_num_states))
+                .collect();
         }
+        self.ca_grid_tmp.resize(num_cells, 0);
 
-        std::mem::swap(&mut self.cells, &mut self.cells_tmp);
+        self.automaton
+            .step(&self.ca_grid, width, height, &mut self.ca_grid_tmp);
+        std::mem::swap(&mut self.ca_grid, &mut self.ca_grid_tmp);
+
+        for (cell, &state) in self.cells.iter_mut().zip(self.ca_grid.iter()) {
+            cell.density = Vec4::broadcast(automaton::dequantize(state, self.ca_num_states));
+        }
     }
 
     fn update_cells(&mut self) {
@@ -100,8 +308,20 @@ impl World {
             self.ticks_per_frame
         };
         for _ in 0..count {
-            for cell in &mut self.cells {
-                cell.step(self.matrix, self.time_delta);
+            let matrix = self.matrix;
+            let time_delta = self.time_delta;
+            match self.update_mode {
+                UpdateMode::Linear => self
+                    .cells
+                    .par_iter_mut()
+                    .for_each(|cell| cell.step(matrix, time_delta)),
+                UpdateMode::Network => {
+                    let network = &self.network;
+                    self.cells
+                        .par_iter_mut()
+                        .for_each(|cell| cell.step_network(network, time_delta))
+                }
+                UpdateMode::Automaton => self.step_automaton(),
             }
             if self.diffuse_enabled {
                 self.diffuse_pass();
@@ -123,7 +343,21 @@ impl App for World {
     }
 
     fn update(&mut self, pixels: &mut Pixels, ctx: &egui::Context) {
+        const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+        let now = std::time::Instant::now();
+        if let Some(last) = self.last_update_instant {
+            let dt = (now - last).as_secs_f32();
+            self.avg_frame_secs = if self.avg_frame_secs == 0.0 {
+                dt
+            } else {
+                self.avg_frame_secs + (dt - self.avg_frame_secs) * FRAME_TIME_SMOOTHING
+            };
+        }
+        self.last_update_instant = Some(now);
+
         let mut changed_size = false;
+        let mut loaded_preset = None;
 
         let mut isolate_color = [false; 4];
 
@@ -195,6 +429,171 @@ impl App for World {
                     dbg!(self.matrix);
                 }
 
+                ui.group(|ui| {
+                    ui.label("Update Mode");
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.update_mode, UpdateMode::Linear, "Linear");
+                        ui.selectable_value(
+                            &mut self.update_mode,
+                            UpdateMode::Network,
+                            "Network",
+                        );
+                        ui.selectable_value(
+                            &mut self.update_mode,
+                            UpdateMode::Automaton,
+                            "Automaton",
+                        );
+                    });
+
+                    if self.update_mode == UpdateMode::Network {
+                        ui.horizontal(|ui| {
+                            ui.label("Hidden layers");
+                            ui.text_edit_singleline(&mut self.network_topology_text);
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Activation");
+                            egui::ComboBox::from_id_source("network_activation")
+                                .selected_text(match self.network.activation {
+                                    Activation::Tanh => "Tanh",
+                                    Activation::Relu => "ReLU",
+                                    Activation::Sigmoid => "Sigmoid",
+                                })
+                                .show_ui(ui, |ui| {
+                                    for (activation, text) in [
+                                        (Activation::Tanh, "Tanh"),
+                                        (Activation::Relu, "ReLU"),
+                                        (Activation::Sigmoid, "Sigmoid"),
+                                    ] {
+                                        if ui
+                                            .selectable_label(
+                                                self.network.activation == activation,
+                                                text,
+                                            )
+                                            .clicked()
+                                        {
+                                            self.network.activation = activation;
+                                            self.network = Network::new(
+                                                self.network.hidden_sizes.clone(),
+                                                activation,
+                                                self.network.use_resources,
+                                            );
+                                        }
+                                    }
+                                });
+                        });
+
+                        if ui.button("Rebuild network").clicked() {
+                            let hidden_sizes: Vec<usize> = self
+                                .network_topology_text
+                                .split(',')
+                                .filter_map(|s| s.trim().parse().ok())
+                                .collect();
+                            self.network = Network::new(
+                                hidden_sizes,
+                                self.network.activation,
+                                self.network.use_resources,
+                            );
+                        }
+                    }
+
+                    if self.update_mode == UpdateMode::Automaton {
+                        ui.add(
+                            egui::Slider::new(&mut self.ca_num_states, 2..=16)
+                                .text("States"),
+                        );
+
+                        ui.label("Rules").on_hover_text(
+                            "GROUP <idx> = <state>,<state>,...\n\
+                             RULE <w>x<h> | <from>:<to> <from>:<to> ...\n\
+                             from: * (Any), <n> (One), g<idx> (Group)\n\
+                             to: - (None), <n> (One), g<idx> (GroupRandom), c<dx>,<dy> (Copy)",
+                        );
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.rules_text)
+                                .desired_rows(4)
+                                .code_editor(),
+                        );
+                        if ui.button("Rebuild rules").clicked() {
+                            let (rules, cell_groups) = automaton::parse_rules_text(&self.rules_text);
+                            self.automaton.rules = rules;
+                            self.automaton.cell_groups = cell_groups;
+                        }
+                    }
+                });
+
+                ui.group(|ui| {
+                    ui.label("Evolve Weights")
+                        .on_hover_text("Evolve the weights matrix toward a fitness goal");
+
+                    ui.horizontal(|ui| {
+                        ui.label("Fitness");
+                        egui::ComboBox::from_id_source("ga_fitness_mode")
+                            .selected_text(match self.ga.config.fitness_mode {
+                                FitnessMode::Variance => "Pattern variance",
+                                FitnessMode::Stability => "Stability",
+                            })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut self.ga.config.fitness_mode,
+                                    FitnessMode::Variance,
+                                    "Pattern variance",
+                                );
+                                ui.selectable_value(
+                                    &mut self.ga.config.fitness_mode,
+                                    FitnessMode::Stability,
+                                    "Stability",
+                                );
+                            });
+                    });
+
+                    ui.add(
+                        egui::Slider::new(&mut self.ga.config.population_size, 4..=256)
+                            .text("Population")
+                            .logarithmic(true),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.ga.config.generations_per_batch, 1..=100)
+                            .text("Generations/batch")
+                            .logarithmic(true),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.ga.config.mut_rate, 0.0..=1.0).text("Mutation rate"),
+                    );
+
+                    if ui.button("Run batch").clicked() {
+                        let best = self.ga.run_batch(&self.cells, self.time_delta);
+                        self.matrix = best.matrix;
+                        self.diffuse_strength = best.diffuse_strength;
+                    }
+                    ui.label(format!("Best fitness: {:.4}", self.ga.best_fitness));
+                });
+
+                ui.group(|ui| {
+                    ui.label("Presets");
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.preset_name);
+                        if ui.button("Save").clicked() && !self.preset_name.is_empty() {
+                            if let Err(err) = self.save_preset(&self.preset_name.clone()) {
+                                eprintln!("failed to save preset: {err}");
+                            }
+                        }
+                    });
+
+                    egui::ComboBox::from_id_source("preset_list")
+                        .selected_text(&self.preset_name)
+                        .show_ui(ui, |ui| {
+                            for name in Self::list_presets() {
+                                if ui.selectable_label(false, &name).clicked() {
+                                    match Self::load_preset(&name) {
+                                        Ok(world) => loaded_preset = Some(world),
+                                        Err(err) => eprintln!("failed to load preset: {err}"),
+                                    }
+                                }
+                            }
+                        });
+                });
+
                 ui.group(|ui| {
                     changed_size |= ui
                         .add(
@@ -232,29 +631,124 @@ impl App for World {
                 self.snapshot.clear();
             }
 
+            ui.group(|ui| {
+                ui.label("Recording (Y4M)");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.record_path);
+                    if self.recorder.is_recording() {
+                        if ui.button("Stop").clicked() {
+                            self.recorder.stop();
+                        }
+                    } else if ui.button("Start").clicked() && !self.record_path.is_empty() {
+                        // Derive the Y4M framerate from the measured real-time
+                        // interval between update() calls (i.e. how often
+                        // write_frame is actually invoked), not time_delta --
+                        // the simulation's per-tick dt has no fixed
+                        // relationship to that cadence.
+                        let measured_frame_secs = if self.avg_frame_secs > 0.0 {
+                            self.avg_frame_secs
+                        } else {
+                            1.0 / 60.0
+                        };
+                        let framerate = (1.0 / measured_frame_secs.max(1.0 / 240.0)).round() as u32;
+                        if let Err(err) =
+                            self.recorder
+                                .start(&self.record_path, self.pixels_size, framerate)
+                        {
+                            eprintln!("failed to start recording: {err}");
+                        }
+                    }
+                });
+            });
+
+            ui.checkbox(&mut self.psd_enabled, "PSD (Welch)");
+            if self.psd_enabled {
+                ui.horizontal(|ui| {
+                    ui.label("Detrend");
+                    egui::ComboBox::from_id_source("psd_detrend")
+                        .selected_text(match self.psd_config.detrend {
+                            DetrendMethod::Midpoint => "Midpoint",
+                            DetrendMethod::Mean => "Mean",
+                            DetrendMethod::LinearFit => "Linear fit",
+                        })
+                        .show_ui(ui, |ui| {
+                            for (method, text) in [
+                                (DetrendMethod::Midpoint, "Midpoint"),
+                                (DetrendMethod::Mean, "Mean"),
+                                (DetrendMethod::LinearFit, "Linear fit"),
+                            ] {
+                                ui.selectable_value(&mut self.psd_config.detrend, method, text);
+                            }
+                        });
+                });
+                ui.add(
+                    egui::Slider::new(&mut self.psd_config.segment_log2_len, 3..=10)
+                        .text("log2(segment length)"),
+                );
+                ui.add(egui::Slider::new(&mut self.psd_config.min_count, 0..=32).text("Min count"));
+                ui.add(egui::Slider::new(&mut self.psd_config.alpha, -1.0..=8.0).text("EMA alpha"));
+            }
+
             use egui::plot::*;
-            Plot::new("Snapshot").show(ui, |plot_ui| {
-                let coord = 0; // todo
-                let densities: Vec<Vec4> = self
-                    .snapshot
-                    .iter()
-                    .map(|cells| cells[coord].density)
-                    .collect();
+            let coord = 0; // todo
+
+            if self.psd_enabled {
+                self.psd_ema.resize_with(4, ChannelEma::default);
 
-                for i in 0..4 {
-                    let values = Values::from_values_iter(
-                        densities
+                let channel_stages: Vec<Vec<(Vec<f32>, Vec<f32>)>> = (0..4)
+                    .map(|i| {
+                        let series: Vec<f32> = self
+                            .snapshot
                             .iter()
-                            .map(|density| density[i])
-                            .enumerate()
-                            .map(|(x, y)| Value::new(x as f64, y)),
-                    );
+                            .map(|cells| cells[coord].density[i])
+                            .collect();
+                        let stages = crate::psd::welch_cascade(&series, &self.psd_config);
+                        self.psd_ema[i].update(&stages, self.psd_config.alpha)
+                    })
+                    .collect();
 
-                    plot_ui.line(Line::new(values));
-                }
-            });
+                Plot::new("PSD").show(ui, |plot_ui| {
+                    for stages in &channel_stages {
+                        for (freqs, psd) in stages {
+                            let values = Values::from_values_iter(freqs.iter().zip(psd).filter_map(
+                                |(&f, &p)| {
+                                    (f > 0.0 && p > 0.0)
+                                        .then(|| Value::new(f.log10() as f64, p.log10() as f64))
+                                },
+                            ));
+                            plot_ui.line(Line::new(values));
+                        }
+                    }
+                });
+            } else {
+                Plot::new("Snapshot").show(ui, |plot_ui| {
+                    let densities: Vec<Vec4> = self
+                        .snapshot
+                        .iter()
+                        .map(|cells| cells[coord].density)
+                        .collect();
+
+                    for i in 0..4 {
+                        let values = Values::from_values_iter(
+                            densities
+                                .iter()
+                                .map(|density| density[i])
+                                .enumerate()
+                                .map(|(x, y)| Value::new(x as f64, y)),
+                        );
+
+                        plot_ui.line(Line::new(values));
+                    }
+                });
+            }
         });
 
+        if let Some(world) = loaded_preset {
+            *self = world;
+            pixels.resize_buffer(self.pixels_size.0, self.pixels_size.1);
+            return;
+        }
+
         if changed_size {
             pixels.resize_buffer(self.pixels_size.0, self.pixels_size.1);
             self.randomize();
@@ -284,5 +778,11 @@ impl App for World {
             }
             pixel.copy_from_slice(&[f.x as _, f.y as _, f.z as _, 0xFF]);
         }
+
+        if self.recorder.is_recording() {
+            if let Err(err) = self.recorder.write_frame(frame) {
+                eprintln!("failed to write recorded frame: {err}");
+            }
+        }
     }
 }